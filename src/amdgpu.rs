@@ -5,6 +5,9 @@ use std::path::{PathBuf, Path};
 use std::{io, fs, fmt};
 use std::io::{BufRead};
 
+use log::warn;
+use serde::{Deserialize, Deserializer};
+
 #[derive(Debug)]
 pub enum GpuError {
     /// IO failure while accessing the GPU device files
@@ -51,6 +54,13 @@ impl fmt::Display for Temperature {
 pub struct Pwm(i32);
 
 impl Pwm {
+    /// The raw PWM value for a fully stopped fan, e.g. for cards with a
+    /// zero-RPM mode. Unlike `from_percentage`, it is not bounded below by
+    /// `pwm_min`.
+    pub fn zero() -> Pwm {
+        Pwm(0)
+    }
+
     pub fn as_raw(self) -> i32 {
         self.0
     }
@@ -67,18 +77,90 @@ impl Pwm {
     }
 }
 
+#[cfg(test)]
+impl Pwm {
+    /// Builds a `Pwm` from a raw value directly, for use in tests elsewhere
+    /// in the crate that need to set up arbitrary `pwm_min`/`pwm_max` pairs.
+    pub(crate) fn raw(value: i32) -> Pwm {
+        Pwm(value)
+    }
+}
+
+/// A GPU voltage reading, in millivolts.
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Copy, Clone)]
+pub struct Voltage(i32);
+
+impl Voltage {
+    pub fn as_volts(self) -> f64 {
+        self.0 as f64 / 1000.0
+    }
+}
+
+impl fmt::Display for Voltage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:.3}V", self.as_volts())
+    }
+}
+
+/// A GPU power reading, in microwatts.
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Copy, Clone)]
+pub struct Power(i64);
+
+impl Power {
+    pub fn as_watts(self) -> f64 {
+        self.0 as f64 / 1_000_000.0
+    }
+}
+
+impl fmt::Display for Power {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:.1}W", self.as_watts())
+    }
+}
+
 #[derive(Debug, Eq, PartialEq, Copy, Clone)]
 pub enum PwmMode {
     Manual,
     Automatic
 }
 
+/// How to combine several `tempX_input` sensors (edge, junction, memory, ...)
+/// into the single reading the control curve reacts to.
+#[derive(Debug, Clone, Default)]
+pub enum TempSource {
+    /// React to the hottest sensor. This is the default, since it's usually
+    /// the junction/hotspot temperature, which is what you want to protect.
+    #[default]
+    Max,
+    /// React to the average of all sensors.
+    Mean,
+    /// React to one specific sensor file, e.g. `"temp2_input"`.
+    Input(String),
+}
+
+impl<'de> Deserialize<'de> for TempSource {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: Deserializer<'de> {
+        let value = String::deserialize(deserializer)?;
+        Ok(match value.as_str() {
+            "max" => TempSource::Max,
+            "mean" => TempSource::Mean,
+            _ => TempSource::Input(value),
+        })
+    }
+}
+
 pub struct Hwmon {
-    path_temperature: PathBuf,
+    hwmon_path: PathBuf,
+    temperature_paths: Vec<PathBuf>,
     path_pwm_enable: PathBuf,
     path_pwm: PathBuf,
     pwm_min: Pwm,
     pwm_max: Pwm,
+    // Telemetry sensors are not exposed by every card, so their paths are optional.
+    path_voltage: Option<PathBuf>,
+    path_power_average: Option<PathBuf>,
+    path_power_cap: Option<PathBuf>,
+    path_frequency: Option<PathBuf>,
 }
 
 impl Hwmon {
@@ -97,22 +179,77 @@ impl Hwmon {
     }
 
     pub fn new<P: AsRef<Path>>(hwmon_path: P) -> Result<Self, GpuError> {
-        let pwm_min_path = hwmon_path.as_ref().join("pwm1_min");
-        let pwm_max_path = hwmon_path.as_ref().join("pwm1_max");
+        let hwmon_path = hwmon_path.as_ref().to_owned();
+        let pwm_min_path = hwmon_path.join("pwm1_min");
+        let pwm_max_path = hwmon_path.join("pwm1_max");
         let pwm_min_raw = Self::read_value(&pwm_min_path)?;
         let pwm_max_raw = Self::read_value(&pwm_max_path)?;
 
+        let mut temperature_paths = Self::find_temperature_inputs(&hwmon_path)?;
+        temperature_paths.sort();
+
         Ok(Hwmon {
-            path_temperature: hwmon_path.as_ref().join("temp1_input"),
-            path_pwm_enable: hwmon_path.as_ref().join("pwm1_enable"),
-            path_pwm: hwmon_path.as_ref().join("pwm1"),
+            path_pwm_enable: hwmon_path.join("pwm1_enable"),
+            path_pwm: hwmon_path.join("pwm1"),
+            path_voltage: Self::optional_path(&hwmon_path, "in0_input"),
+            path_power_average: Self::optional_path(&hwmon_path, "power1_average"),
+            path_power_cap: Self::optional_path(&hwmon_path, "power1_cap"),
+            path_frequency: Self::optional_path(&hwmon_path, "freq1_input"),
+            hwmon_path,
+            temperature_paths,
             pwm_min: Pwm(pwm_min_raw),
             pwm_max: Pwm(pwm_max_raw),
         })
     }
 
-    pub fn get_temperature(&self) -> Result<Temperature, GpuError> {
-        let temp_raw = Self::read_value(&self.path_temperature)?;
+    /// Finds every `tempX_input` sensor file exposed by this hwmon device.
+    fn find_temperature_inputs(hwmon_path: &Path) -> Result<Vec<PathBuf>, GpuError> {
+        let mut result = Vec::new();
+        for entry in fs::read_dir(hwmon_path)? {
+            let entry = entry?;
+            let file_name = entry.file_name();
+            let name = file_name.to_string_lossy();
+            if name.starts_with("temp") && name.ends_with("_input") {
+                result.push(entry.path());
+            }
+        }
+        Ok(result)
+    }
+
+    fn optional_path(hwmon_path: &Path, file_name: &str) -> Option<PathBuf> {
+        let path = hwmon_path.join(file_name);
+        if path.exists() {
+            Some(path)
+        } else {
+            None
+        }
+    }
+
+    pub fn get_temperature(&self, source: &TempSource) -> Result<Temperature, GpuError> {
+        if let TempSource::Input(name) = source {
+            let temp_raw = Self::read_value(self.hwmon_path.join(name))?;
+            return Ok(Temperature(temp_raw));
+        }
+
+        let readings: Vec<i32> = self.temperature_paths.iter()
+            .filter_map(|path| match Self::read_value::<_, i32>(path) {
+                Ok(value) => Some(value),
+                Err(err) => {
+                    warn!("{}", err);
+                    None
+                },
+            })
+            .collect();
+
+        if readings.is_empty() {
+            return Err(GpuError::Parse(self.hwmon_path.clone(), None));
+        }
+
+        let temp_raw = match source {
+            TempSource::Max => readings.into_iter().max().unwrap(),
+            TempSource::Mean => (readings.iter().sum::<i32>() as f64 / readings.len() as f64).round() as i32,
+            TempSource::Input(_) => unreachable!(),
+        };
         Ok(Temperature(temp_raw))
     }
 
@@ -132,11 +269,36 @@ impl Hwmon {
         Self::write_value(&self.path_pwm_enable, value)
     }
 
+    pub fn get_pwm(&self) -> Result<Pwm, GpuError> {
+        let pwm_raw = Self::read_value(&self.path_pwm)?;
+        Ok(Pwm(pwm_raw))
+    }
+
     pub fn set_pwm(&mut self, value: Pwm) -> Result<(), GpuError> {
         let value_str = format!("{}\n", value.0);
         Self::write_value(&self.path_pwm, &value_str)
     }
 
+    /// The GPU core voltage, if the card exposes `in0_input`.
+    pub fn get_voltage(&self) -> Option<Result<Voltage, GpuError>> {
+        self.path_voltage.as_ref().map(|path| Self::read_value(path).map(Voltage))
+    }
+
+    /// The GPU's current average power draw, if the card exposes `power1_average`.
+    pub fn get_power_average(&self) -> Option<Result<Power, GpuError>> {
+        self.path_power_average.as_ref().map(|path| Self::read_value(path).map(Power))
+    }
+
+    /// The GPU's configured power cap, if the card exposes `power1_cap`.
+    pub fn get_power_cap(&self) -> Option<Result<Power, GpuError>> {
+        self.path_power_cap.as_ref().map(|path| Self::read_value(path).map(Power))
+    }
+
+    /// The GPU core clock, in Hz, if the card exposes `freq1_input`.
+    pub fn get_frequency(&self) -> Option<Result<i64, GpuError>> {
+        self.path_frequency.as_ref().map(Self::read_value)
+    }
+
     fn read_value<P: AsRef<Path>, V: std::str::FromStr>(path: P) -> Result<V, GpuError> {
         let file = fs::File::open(path.as_ref())?;
         let reader = io::BufReader::new(file);
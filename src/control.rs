@@ -1,3 +1,6 @@
+use crate::amdgpu::{GpuError, Pwm};
+
+#[derive(Debug, Clone)]
 pub struct ControlCurve {
     data_points: Vec<(f64, f64)>,
 }
@@ -34,11 +37,95 @@ impl ControlCurve {
             low_y + (high_y - low_y) * (input - low_x) / (high_x - low_x)
         }
     }
+
+    /// The fan speed at the lowest defined temperature, i.e. the speed the
+    /// curve clamps to below its first data point.
+    pub fn min_speed(&self) -> f64 {
+        self.data_points.first().map(|(_, speed)| *speed).unwrap_or(0.0)
+    }
+}
+
+/// Tracks the spin-up pulse needed to get a fan moving reliably whenever the
+/// curve asks for a nonzero duty below the card's minimum spinnable speed.
+/// Many cards will stall if driven straight from a stop into such a low duty,
+/// so we briefly pulse at `spinup_percentage` before settling to the target.
+pub struct SpinupController {
+    min_active_percentage: f64,
+    spinup_percentage: f64,
+    spinup_ticks: u32,
+    ticks_remaining: u32,
+    spun_up: bool,
+}
+
+impl SpinupController {
+    pub fn new(min_active_percentage: f64, spinup_percentage: f64, spinup_ticks: u32) -> SpinupController {
+        SpinupController {
+            min_active_percentage,
+            spinup_percentage,
+            spinup_ticks,
+            ticks_remaining: 0,
+            spun_up: false,
+        }
+    }
+
+    /// Given the curve's raw target percentage for this tick, returns the
+    /// percentage that should actually be applied, accounting for any
+    /// in-progress spin-up pulse.
+    pub fn apply(&mut self, target_percentage: f64) -> f64 {
+        if target_percentage <= 0.0 || target_percentage >= self.min_active_percentage {
+            self.ticks_remaining = 0;
+            self.spun_up = false;
+            return target_percentage;
+        }
+
+        if !self.spun_up && self.ticks_remaining == 0 {
+            self.ticks_remaining = self.spinup_ticks;
+        }
+
+        if self.ticks_remaining > 0 {
+            self.ticks_remaining -= 1;
+            if self.ticks_remaining == 0 {
+                self.spun_up = true;
+            }
+            self.spinup_percentage
+        } else {
+            target_percentage
+        }
+    }
+}
+
+/// Decides whether a new PWM value is worth writing, given the temperature
+/// and PWM in effect as of the last write (not the last poll). This is the
+/// hysteresis/deadband described on `hysteresis_celsius`: a write happens
+/// only once the temperature has moved far enough from the one that
+/// produced the currently-applied PWM, or the target PWM itself changed.
+pub fn should_write_pwm(last_write_temperature: Option<f64>, temperature_celcius: f64, hysteresis_celsius: f64, last_pwm: Option<Pwm>, fan_speed_pwm: Pwm) -> bool {
+    let temperature_changed = last_write_temperature
+        .map(|last| (temperature_celcius - last).abs() > hysteresis_celsius)
+        .unwrap_or(true);
+    let pwm_changed = last_pwm != Some(fan_speed_pwm);
+    temperature_changed || pwm_changed
+}
+
+/// Resolves a curve's relative fan speed target into an actual `Pwm` value.
+///
+/// A target of `0.0` is handled explicitly: only cards known to support
+/// zero-RPM / fan-stop (`fan_stop_enabled`) get the true raw `0` written,
+/// since pulsing a fan back up from a full stop isn't possible on cards
+/// without that mode. Everything else falls back to `pwm_min`, matching
+/// the previous (pre-zero-RPM) behaviour of `Pwm::from_percentage`.
+pub fn resolve_pwm(fan_speed_relative: f64, pwm_min: Pwm, pwm_max: Pwm, fan_stop_enabled: bool) -> Result<Pwm, GpuError> {
+    if fan_speed_relative <= 0.0 && fan_stop_enabled {
+        Ok(Pwm::zero())
+    } else {
+        Pwm::from_percentage(pwm_min, pwm_max, fan_speed_relative)
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use super::ControlCurve;
+    use super::{resolve_pwm, should_write_pwm, ControlCurve, SpinupController};
+    use crate::amdgpu::Pwm;
 
     fn make_test_curve() -> ControlCurve {
         ControlCurve {
@@ -67,4 +154,82 @@ mod test {
         assert_eq!(curve.control(20.0), 7.5);
         assert_eq!(curve.control(45.0), 40.0);
     }
+
+    #[test]
+    fn spinup_pulses_then_settles_below_minimum() {
+        let mut spinup = SpinupController::new(0.2, 1.0, 3);
+
+        assert_eq!(spinup.apply(0.05), 1.0);
+        assert_eq!(spinup.apply(0.05), 1.0);
+        assert_eq!(spinup.apply(0.05), 1.0);
+        assert_eq!(spinup.apply(0.05), 0.05);
+        assert_eq!(spinup.apply(0.05), 0.05);
+    }
+
+    #[test]
+    fn spinup_skipped_above_minimum() {
+        let mut spinup = SpinupController::new(0.2, 1.0, 3);
+        assert_eq!(spinup.apply(0.5), 0.5);
+        assert_eq!(spinup.apply(0.5), 0.5);
+    }
+
+    #[test]
+    fn spinup_zero_target_passes_through_and_resets() {
+        let mut spinup = SpinupController::new(0.2, 1.0, 3);
+        assert_eq!(spinup.apply(0.05), 1.0);
+        assert_eq!(spinup.apply(0.0), 0.0);
+        // Dropping to zero resets the state, so rising back into the
+        // below-minimum range triggers a fresh spin-up pulse.
+        assert_eq!(spinup.apply(0.05), 1.0);
+    }
+
+    #[test]
+    fn spinup_retriggers_after_returning_above_minimum() {
+        let mut spinup = SpinupController::new(0.2, 1.0, 2);
+        assert_eq!(spinup.apply(0.05), 1.0);
+        assert_eq!(spinup.apply(0.05), 1.0);
+        assert_eq!(spinup.apply(0.05), 0.05);
+        assert_eq!(spinup.apply(0.5), 0.5);
+        assert_eq!(spinup.apply(0.05), 1.0);
+    }
+
+    #[test]
+    fn resolve_pwm_zero_target_without_fan_stop_clamps_to_min() {
+        let pwm = resolve_pwm(0.0, Pwm::raw(40), Pwm::raw(255), false).unwrap();
+        assert_eq!(pwm, Pwm::raw(40));
+    }
+
+    #[test]
+    fn resolve_pwm_zero_target_with_fan_stop_writes_true_zero() {
+        let pwm = resolve_pwm(0.0, Pwm::raw(40), Pwm::raw(255), true).unwrap();
+        assert_eq!(pwm, Pwm::raw(0));
+    }
+
+    #[test]
+    fn resolve_pwm_nonzero_target_ignores_fan_stop_flag() {
+        let with_fan_stop = resolve_pwm(1.0, Pwm::raw(40), Pwm::raw(255), true).unwrap();
+        let without_fan_stop = resolve_pwm(1.0, Pwm::raw(40), Pwm::raw(255), false).unwrap();
+        assert_eq!(with_fan_stop, Pwm::raw(255));
+        assert_eq!(without_fan_stop, Pwm::raw(255));
+    }
+
+    #[test]
+    fn should_write_pwm_first_tick_always_writes() {
+        assert!(should_write_pwm(None, 50.0, 2.0, None, Pwm::raw(100)));
+    }
+
+    #[test]
+    fn should_write_pwm_skipped_within_deadband_and_unchanged_pwm() {
+        assert!(!should_write_pwm(Some(50.0), 51.0, 2.0, Some(Pwm::raw(100)), Pwm::raw(100)));
+    }
+
+    #[test]
+    fn should_write_pwm_triggers_once_temperature_exceeds_hysteresis() {
+        assert!(should_write_pwm(Some(50.0), 53.0, 2.0, Some(Pwm::raw(100)), Pwm::raw(100)));
+    }
+
+    #[test]
+    fn should_write_pwm_triggers_on_pwm_change_even_within_deadband() {
+        assert!(should_write_pwm(Some(50.0), 50.5, 2.0, Some(Pwm::raw(100)), Pwm::raw(120)));
+    }
 }
\ No newline at end of file
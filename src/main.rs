@@ -9,7 +9,7 @@ use signal_hook;
 
 mod amdgpu;
 mod control;
-use control::ControlCurve;
+use control::{resolve_pwm, should_write_pwm, ControlCurve, SpinupController};
 
 fn main() {
     env_logger::from_env(
@@ -24,55 +24,192 @@ fn main() {
     }
 }
 
+/// What the daemon should do once it has a configuration and a set of cards.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+enum Mode {
+    /// Disable native fan control and drive the fan curve (the original behaviour).
+    Service,
+    /// Only observe and print temperature/PWM/curve readings, without touching the fan.
+    Monitor,
+}
+
+/// A minimal argument parser: we only have one optional positional subcommand.
+fn parse_args() -> Mode {
+    match std::env::args().nth(1).as_deref() {
+        None | Some("service") => Mode::Service,
+        Some("monitor") => Mode::Monitor,
+        Some(other) => {
+            warn!("Unknown subcommand '{}', falling back to 'service'. Valid subcommands are 'service' and 'monitor'.", other);
+            Mode::Service
+        },
+    }
+}
+
+struct Card {
+    path: std::path::PathBuf,
+    device: amdgpu::Hwmon,
+    curve: ControlCurve,
+    spinup: SpinupController,
+    last_temperature: Option<f64>,
+    last_pwm: Option<amdgpu::Pwm>,
+}
+
 fn run() -> Result<(), Error> {
+    let mode = parse_args();
+
     let config_files = vec![
         "amdgpu-fan.toml",
         "/etc/amdgpu-fan.toml",
     ];
     let config = load_config(config_files.iter())?;
 
-    info!("Card: {}", config.control.card_path.display());
+    info!("Mode: {:?}", mode);
     info!("Poll: {}ms", config.control.poll_interval_millis);
 
-    let mut hwmons = amdgpu::Hwmon::for_device(config.control.card_path)?;
-    let mut device = hwmons.pop().ok_or(Error::CouldNotFindDevice)?;
-
     let exit = Arc::new(AtomicBool::new(false));
     signal_hook::flag::register(signal_hook::SIGTERM, Arc::clone(&exit))?;
     signal_hook::flag::register(signal_hook::SIGINT, Arc::clone(&exit))?;
 
-    device.set_pwm_mode(amdgpu::PwmMode::Manual)?;
-    info!("Native fan control disabled");
+    let default_curve = config.curve.to_curve();
+    let take_control = mode == Mode::Service;
+
+    let mut cards: Vec<Card> = Vec::new();
+    for card in &config.control.cards {
+        match init_card(card, &default_curve, &config.control, take_control) {
+            Ok(c) => cards.push(c),
+            Err(err) => warn!("Skipping card: {}", err),
+        }
+    }
+
+    if cards.is_empty() {
+        return Err(Error::NoUsableCards);
+    }
 
-    let curve = config.curve.to_curve();
     let poll_interval = time::Duration::from_millis(config.control.poll_interval_millis);
 
-    let result = control_loop(&mut device, poll_interval, &curve, exit);
+    match mode {
+        Mode::Service => {
+            let result = control_loop(
+                &mut cards,
+                poll_interval,
+                &config.control.temp_source,
+                config.control.hysteresis_celsius,
+                config.control.fan_start_temp,
+                config.control.fan_stop_enabled,
+                exit,
+            );
+
+            if let Err(_) = &result {
+                info!("Control loop aborted");
+            } else {
+                info!("Control loop stopped");
+            }
 
-    if let Err(_) = &result {
-        info!("Control loop aborted");
-    } else {
-        info!("Control loop stopped");
+            for card in cards.iter_mut() {
+                if let Err(err) = card.device.set_pwm_mode(amdgpu::PwmMode::Automatic) {
+                    error!("{}: could not restore native fan control: {}", card.path.display(), err);
+                } else {
+                    info!("{}: native fan control restored", card.path.display());
+                }
+            }
+
+            result.map_err(Into::into)
+        },
+        Mode::Monitor => {
+            monitor_loop(&mut cards, poll_interval, &config.control.temp_source, exit).map_err(Into::into)
+        },
     }
+}
+
+fn init_card(card: &CardConfig, default_curve: &ControlCurve, control: &ControlConfig, take_control: bool) -> Result<Card, Error> {
+    let mut hwmons = amdgpu::Hwmon::for_device(&card.path)?;
+    let mut device = hwmons.pop().ok_or_else(|| Error::CouldNotFindDevice(card.path.clone()))?;
 
-    if let Err(err) = device.set_pwm_mode(amdgpu::PwmMode::Automatic) {
-        error!("Could not restore native fan control: {}", err);
-    } else {
-        info!("Native fan control restored");
+    if take_control {
+        device.set_pwm_mode(amdgpu::PwmMode::Manual)?;
+        info!("{}: native fan control disabled", card.path.display());
     }
 
-    result.map_err(Into::into)
+    let curve = card.curve.as_ref()
+        .map(CurveConfig::to_curve)
+        .unwrap_or_else(|| default_curve.clone());
+
+    let spinup = SpinupController::new(control.min_active_pwm_percentage, control.spinup_pwm_percentage, control.spinup_ticks);
+
+    Ok(Card { path: card.path.clone(), device, curve, spinup, last_temperature: None, last_pwm: None })
+}
+
+/// Formats an optional telemetry reading for display, falling back to "n/a"
+/// if the sensor is missing or failed to parse; only a failed read or parse
+/// logs a warning.
+fn format_optional<T: std::fmt::Display>(reading: Option<Result<T, amdgpu::GpuError>>) -> String {
+    match reading {
+        Some(Ok(value)) => value.to_string(),
+        Some(Err(err)) => {
+            warn!("{}", err);
+            "n/a".to_owned()
+        },
+        None => "n/a".to_owned(),
+    }
 }
 
-fn control_loop(device: &mut amdgpu::Hwmon, poll_interval: time::Duration, curve: &ControlCurve, exit_var: Arc<AtomicBool>) -> Result<(), amdgpu::GpuError> {
+fn control_loop(cards: &mut [Card], poll_interval: time::Duration, temp_source: &amdgpu::TempSource, hysteresis_celsius: f64, fan_start_temp: Option<f64>, fan_stop_enabled: bool, exit_var: Arc<AtomicBool>) -> Result<(), amdgpu::GpuError> {
     while !exit_var.load(Ordering::Relaxed) {
-        let temperature_celcius = device.get_temperature()?.as_celcius();
-        let fan_speed_relative = curve.control(temperature_celcius);
-        let fan_speed_pwm = amdgpu::Pwm::from_percentage(device.get_pwm_min(), device.get_pwm_max(), fan_speed_relative)?;
+        for card in cards.iter_mut() {
+            let temperature_celcius = card.device.get_temperature(temp_source)?.as_celcius();
 
-        debug!("T_cur={: >5.1}°C\tV_rel={: >5.1}%\tV_pwm={: >3}", temperature_celcius, fan_speed_relative * 100.0, fan_speed_pwm.as_raw());
+            let curve_target = match fan_start_temp {
+                Some(start) if temperature_celcius < start => card.curve.min_speed(),
+                _ => card.curve.control(temperature_celcius),
+            };
+            let fan_speed_relative = card.spinup.apply(curve_target);
+            let fan_speed_pwm = resolve_pwm(fan_speed_relative, card.device.get_pwm_min(), card.device.get_pwm_max(), fan_stop_enabled)?;
 
-        device.set_pwm(fan_speed_pwm)?;
+            if should_write_pwm(card.last_temperature, temperature_celcius, hysteresis_celsius, card.last_pwm, fan_speed_pwm) {
+                debug!(
+                    "{}: T_cur={: >5.1}°C\tV_rel={: >5.1}%\tV_pwm={: >3}\tU={}\tP={}",
+                    card.path.display(), temperature_celcius, fan_speed_relative * 100.0, fan_speed_pwm.as_raw(),
+                    format_optional(card.device.get_voltage()),
+                    format_optional(card.device.get_power_average()),
+                );
+
+                card.device.set_pwm(fan_speed_pwm)?;
+                card.last_pwm = Some(fan_speed_pwm);
+                card.last_temperature = Some(temperature_celcius);
+            }
+        }
+
+        thread::sleep(poll_interval);
+    }
+    Ok(())
+}
+
+/// Like `control_loop`, but only observes: it never switches the card to manual
+/// fan control and never writes a PWM value, so it's safe to run alongside the
+/// native fan control (or a separate `service` instance) to tune a curve.
+fn monitor_loop(cards: &mut [Card], poll_interval: time::Duration, temp_source: &amdgpu::TempSource, exit_var: Arc<AtomicBool>) -> Result<(), amdgpu::GpuError> {
+    while !exit_var.load(Ordering::Relaxed) {
+        for card in cards.iter_mut() {
+            let temperature = card.device.get_temperature(temp_source)?;
+            let pwm_current = card.device.get_pwm()?;
+            let pwm_min = card.device.get_pwm_min();
+            let pwm_max = card.device.get_pwm_max();
+            let fan_speed_relative = card.curve.control(temperature.as_celcius());
+
+            println!(
+                "{}\tT_cur={: >5.1}°C\tV_pwm={: >3}\tV_target={: >5.1}%\tpwm_min={: >3}\tpwm_max={: >3}\tU={}\tP={} (cap {})\tfreq={}Hz",
+                card.path.display(),
+                temperature.as_celcius(),
+                pwm_current.as_raw(),
+                fan_speed_relative * 100.0,
+                pwm_min.as_raw(),
+                pwm_max.as_raw(),
+                format_optional(card.device.get_voltage()),
+                format_optional(card.device.get_power_average()),
+                format_optional(card.device.get_power_cap()),
+                format_optional(card.device.get_frequency()),
+            );
+        }
 
         thread::sleep(poll_interval);
     }
@@ -85,7 +222,8 @@ pub enum Error {
     Control(amdgpu::GpuError),
     ConfigurationMissing,
     InvalidCurve,
-    CouldNotFindDevice,
+    CouldNotFindDevice(std::path::PathBuf),
+    NoUsableCards,
 }
 
 impl From<std::io::Error> for Error {
@@ -114,7 +252,8 @@ impl std::fmt::Display for Error {
             &Error::Control(err) => write!(f, "{}", err),
             &Error::InvalidCurve => write!(f, "Curve definition must contain at least one entry, and an equal number of temperatures and fan speeds."),
             &Error::ConfigurationMissing => write!(f, "No valid configuration file found"),
-            &Error::CouldNotFindDevice => write!(f, "No HWMON entry found for the selected card"),
+            &Error::CouldNotFindDevice(path) => write!(f, "No HWMON entry found for {}", path.display()),
+            &Error::NoUsableCards => write!(f, "No configured card could be initialized"),
         }
     }
 }
@@ -143,8 +282,54 @@ impl CurveConfig {
 
 #[derive(Debug, Deserialize)]
 struct ControlConfig {
-    card_path: std::path::PathBuf,
     poll_interval_millis: u64,
+    cards: Vec<CardConfig>,
+    #[serde(default)]
+    temp_source: amdgpu::TempSource,
+    /// Temperature change (in °C) since the last PWM write beyond which the PWM
+    /// value is rewritten even if it is unchanged. The curve is evaluated on
+    /// every poll, and a computed PWM that differs from the last written value
+    /// is always written, regardless of this setting.
+    #[serde(default = "default_hysteresis_celsius")]
+    hysteresis_celsius: f64,
+    /// Below this temperature, the fan is forced to the curve's minimum speed
+    /// regardless of what the curve itself would interpolate to.
+    fan_start_temp: Option<f64>,
+    /// The lowest duty (as a 0.0-1.0 fraction) the fan can spin at reliably.
+    /// Targets below this but above 0.0 trigger a spin-up pulse first.
+    /// Defaults to 0.0, which disables spin-up handling.
+    #[serde(default)]
+    min_active_pwm_percentage: f64,
+    /// The duty used for the spin-up pulse itself.
+    #[serde(default = "default_spinup_pwm_percentage")]
+    spinup_pwm_percentage: f64,
+    /// How many ticks to hold the spin-up pulse before settling to the target.
+    #[serde(default = "default_spinup_ticks")]
+    spinup_ticks: u32,
+    /// Whether the card is known to support zero-RPM / fan-stop, i.e. it can
+    /// reliably spin back up after being driven to a true `0`. When `false`
+    /// (the default), a curve target of `0.0` is clamped to `pwm_min`
+    /// instead, matching behaviour from before zero-RPM support was added.
+    #[serde(default)]
+    fan_stop_enabled: bool,
+}
+
+fn default_hysteresis_celsius() -> f64 {
+    2.0
+}
+
+fn default_spinup_pwm_percentage() -> f64 {
+    1.0
+}
+
+fn default_spinup_ticks() -> u32 {
+    3
+}
+
+#[derive(Debug, Deserialize)]
+struct CardConfig {
+    path: std::path::PathBuf,
+    curve: Option<CurveConfig>,
 }
 
 fn load_config<I, P>(paths_to_check: I) -> Result<Config, Error> where
@@ -176,10 +361,14 @@ fn load_config<I, P>(paths_to_check: I) -> Result<Config, Error> where
 fn load_config_file(path: &Path) -> Result<Config, Error> {
     let contents = std::fs::read_to_string(path)?;
     let config = toml::from_str::<Config>(contents.as_ref())?;
-    if config.curve.temperatures.len() != config.curve.fan_speeds.len()
-        || config.curve.temperatures.is_empty() {
-        Err(Error::InvalidCurve)
-    } else {
-        Ok(config)
+
+    let curves = std::iter::once(&config.curve)
+        .chain(config.control.cards.iter().filter_map(|card| card.curve.as_ref()));
+    for curve in curves {
+        if curve.temperatures.len() != curve.fan_speeds.len() || curve.temperatures.is_empty() {
+            return Err(Error::InvalidCurve);
+        }
     }
+
+    Ok(config)
 }
\ No newline at end of file